@@ -0,0 +1,117 @@
+//! Registering Rust closures (as opposed to bare `extern "C" fn`s) as Lua C functions.
+//!
+//! `reg!`/[`LuaCFunction`] only accept function pointers, so there was previously no way to
+//! register a handler that captures environment. [`push_closure`] boxes a
+//! `dyn FnMut(LuaState) -> i32`, stores it as full userdata upvalue 1 of a `lua_pushcclosure`,
+//! and uses a cached `__gc` metatable to free it once Lua collects the closure.
+
+use std::any::Any;
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::cstr;
+use crate::lua::*;
+use crate::types::LuaState;
+
+/// A boxed Lua-callable closure, as stored behind the userdata upvalue.
+type BoxedClosure = Box<dyn FnMut(LuaState) -> i32>;
+
+/// Registry key under which the shared `__gc` metatable for boxed closures is cached, so it
+/// is only ever built once no matter how many closures get pushed.
+const CLOSURE_METATABLE_NAME: &str = "rglua_closure_metatable\0";
+
+/// `__gc` metamethod: drops the `BoxedClosure` owned by the userdata.
+extern "C" fn closure_gc(l: LuaState) -> c_int {
+	unsafe {
+		let ud = lua_touserdata(l, 1) as *mut BoxedClosure;
+		std::ptr::drop_in_place(ud);
+	}
+	0
+}
+
+/// Ensures the cached `__gc` metatable for boxed closures exists, leaving it on top of the
+/// stack either way.
+fn push_closure_metatable(l: LuaState) {
+	unsafe {
+		if luaL_newmetatable(l, CLOSURE_METATABLE_NAME.as_ptr() as *const i8) != 0 {
+			lua_pushcfunction(l, closure_gc);
+			lua_setfield(l, -2, cstr!("__gc"));
+		}
+	}
+}
+
+/// Trampoline invoked by Lua; recovers the boxed closure from upvalue 1 and calls it,
+/// catching any Rust panic so it can't unwind across the FFI boundary into Lua.
+extern "C" fn closure_trampoline(l: LuaState) -> c_int {
+	let ud = lua_touserdata(l, lua_upvalueindex(1)) as *mut BoxedClosure;
+
+	let result = panic::catch_unwind(AssertUnwindSafe(|| {
+		let closure = unsafe { &mut *ud };
+		closure(l)
+	}));
+
+	match result {
+		Ok(ret) => ret,
+		Err(payload) => {
+			let msg = panic_message(payload);
+			let cmsg = std::ffi::CString::new(msg).unwrap_or_default();
+			unsafe { luaL_error(l, cstr!("rust closure panicked: %s"), cmsg.as_ptr()) }
+		}
+	}
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+	if let Some(s) = payload.downcast_ref::<&str>() {
+		s.to_string()
+	} else if let Some(s) = payload.downcast_ref::<String>() {
+		s.clone()
+	} else {
+		"unknown panic".to_string()
+	}
+}
+
+/// Pushes a boxed Rust closure onto the stack as a callable Lua C function.
+///
+/// The closure is heap-allocated as full Lua userdata so Lua's GC owns its lifetime; a
+/// shared, registry-cached metatable runs the closure's destructor via `__gc` once
+/// collected. Panics inside the closure are caught at the FFI boundary and surfaced as a
+/// Lua error instead of unwinding into Lua's C stack.
+pub fn push_closure<F>(l: LuaState, closure: F)
+where
+	F: FnMut(LuaState) -> i32 + 'static,
+{
+	unsafe {
+		let boxed: BoxedClosure = Box::new(closure);
+		let ud = lua_newuserdata(l, std::mem::size_of::<BoxedClosure>()) as *mut BoxedClosure;
+		ud.write(boxed);
+
+		push_closure_metatable(l);
+		lua_setmetatable(l, -2);
+
+		lua_pushcclosure(l, closure_trampoline, 1);
+	}
+}
+
+/// Registers a Rust closure under `name` in the table on top of the stack, mirroring how
+/// `reg!` registers bare `extern "C" fn`s, but allowing the handler to capture environment.
+/// # Examples
+/// ```rust, no_run
+/// use rglua::prelude::*;
+/// fn gmod13_open(l: LuaState) -> i32 {
+///     let greeting = "hello".to_string();
+///     lua_newtable(l);
+///     reg_closure!(l, "greet", move |l| {
+///         printgm!(l, "{}", greeting);
+///         0
+///     });
+///     lua_setglobal(l, cstr!("MyLibrary"));
+///     0
+/// }
+/// ```
+#[macro_export]
+macro_rules! reg_closure {
+	($l:expr, $name:expr, $closure:expr) => {{
+		rglua::closure::push_closure($l, $closure);
+		rglua::lua::lua_setfield($l, -2, rglua::cstr!($name));
+	}};
+}
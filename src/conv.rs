@@ -0,0 +1,308 @@
+//! High-level, typed conversions between Rust values and values on the Lua stack.
+//!
+//! The rest of the crate only exposes the raw `lua_toX`/`lua_pushX` primitives, which means
+//! every binding has to hand-roll its own `lua_type` checks. [`FromLua`] and [`IntoLua`] build
+//! a zero-cost layer on top of those primitives so argument/return marshalling can be written
+//! once and reused.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::lua::*;
+use crate::types::{LuaInteger, LuaNumber, LuaState};
+
+/// The error returned when a value on the stack doesn't match the type a [`FromLua`]
+/// impl expected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvError {
+	pub expected: Type,
+	pub found: Type,
+	pub idx: i32,
+}
+
+impl std::fmt::Display for ConvError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"expected '{:?}' at stack index {}, found '{:?}'",
+			self.expected, self.idx, self.found
+		)
+	}
+}
+
+impl std::error::Error for ConvError {}
+
+/// Converts a value sitting on the Lua stack at `idx` into a Rust value.
+///
+/// Implementations should never panic; a type mismatch must be reported through
+/// [`ConvError`] so binding authors can turn it into a Lua error themselves.
+pub trait FromLua: Sized {
+	fn from_lua(l: LuaState, idx: i32) -> Result<Self, ConvError>;
+}
+
+/// Pushes a Rust value onto the Lua stack, consuming it.
+pub trait IntoLua {
+	fn into_lua(self, l: LuaState);
+}
+
+macro_rules! impl_number {
+	($ty:ty, $lua_ty:ty) => {
+		impl FromLua for $ty {
+			fn from_lua(l: LuaState, idx: i32) -> Result<Self, ConvError> {
+				match lua_type(l, idx) {
+					Type::Number => Ok(lua_tonumber(l, idx) as $ty),
+					found => Err(ConvError {
+						expected: Type::Number,
+						found,
+						idx,
+					}),
+				}
+			}
+		}
+
+		impl IntoLua for $ty {
+			fn into_lua(self, l: LuaState) {
+				lua_pushnumber(l, self as $lua_ty);
+			}
+		}
+	};
+}
+
+impl_number!(LuaInteger, LuaNumber);
+
+// Not routed through `impl_number!`: `lua_tonumber`/`lua_pushnumber` already speak
+// `LuaNumber` natively, so casting `as LuaNumber` here would be a no-op that trips
+// `clippy::unnecessary_cast`.
+impl FromLua for LuaNumber {
+	fn from_lua(l: LuaState, idx: i32) -> Result<Self, ConvError> {
+		match lua_type(l, idx) {
+			Type::Number => Ok(lua_tonumber(l, idx)),
+			found => Err(ConvError {
+				expected: Type::Number,
+				found,
+				idx,
+			}),
+		}
+	}
+}
+
+impl IntoLua for LuaNumber {
+	fn into_lua(self, l: LuaState) {
+		lua_pushnumber(l, self);
+	}
+}
+
+impl FromLua for bool {
+	fn from_lua(l: LuaState, idx: i32) -> Result<Self, ConvError> {
+		match lua_type(l, idx) {
+			Type::Bool => Ok(lua_toboolean(l, idx) == 1),
+			found => Err(ConvError {
+				expected: Type::Bool,
+				found,
+				idx,
+			}),
+		}
+	}
+}
+
+impl IntoLua for bool {
+	fn into_lua(self, l: LuaState) {
+		lua_pushboolean(l, self as i32);
+	}
+}
+
+impl FromLua for String {
+	fn from_lua(l: LuaState, idx: i32) -> Result<Self, ConvError> {
+		match lua_type(l, idx) {
+			Type::String => {
+				let mut len: usize = 0;
+				let ptr = lua_tolstring(l, idx, &mut len);
+				let bytes = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) };
+				Ok(String::from_utf8_lossy(bytes).into_owned())
+			}
+			found => Err(ConvError {
+				expected: Type::String,
+				found,
+				idx,
+			}),
+		}
+	}
+}
+
+impl IntoLua for String {
+	fn into_lua(self, l: LuaState) {
+		self.as_str().into_lua(l);
+	}
+}
+
+impl IntoLua for &str {
+	fn into_lua(self, l: LuaState) {
+		unsafe { lua_pushlstring(l, self.as_ptr() as *const i8, self.len()) };
+	}
+}
+
+impl<T: FromLua> FromLua for Option<T> {
+	fn from_lua(l: LuaState, idx: i32) -> Result<Self, ConvError> {
+		match lua_type(l, idx) {
+			Type::Nil | Type::None => Ok(None),
+			_ => T::from_lua(l, idx).map(Some),
+		}
+	}
+}
+
+impl<T: IntoLua> IntoLua for Option<T> {
+	fn into_lua(self, l: LuaState) {
+		match self {
+			Some(v) => v.into_lua(l),
+			None => lua_pushnil(l),
+		}
+	}
+}
+
+impl<T: FromLua> FromLua for Vec<T> {
+	fn from_lua(l: LuaState, idx: i32) -> Result<Self, ConvError> {
+		match lua_type(l, idx) {
+			Type::Table => {
+				// Guards against `T::from_lua` erroring mid-loop: the element it was
+				// reading would otherwise be left on the stack forever.
+				let _guard = Stack::new(l);
+				let len = lua_objlen(l, idx);
+				let mut out = Vec::with_capacity(len);
+				for i in 1..=len {
+					lua_rawgeti(l, idx, i as i32);
+					out.push(T::from_lua(l, -1)?);
+					lua_pop(l, 1);
+				}
+				Ok(out)
+			}
+			found => Err(ConvError {
+				expected: Type::Table,
+				found,
+				idx,
+			}),
+		}
+	}
+}
+
+impl<T: IntoLua> IntoLua for Vec<T> {
+	fn into_lua(self, l: LuaState) {
+		lua_createtable(l, self.len() as i32, 0);
+		for (i, v) in self.into_iter().enumerate() {
+			v.into_lua(l);
+			lua_rawseti(l, -2, (i + 1) as i32);
+		}
+	}
+}
+
+/// Normalizes a possibly-negative (relative to the top) stack index to its absolute,
+/// positive form. Needed anywhere a slot's index is read again after pushing further
+/// values onto the stack, since a relative index would otherwise keep shifting.
+fn abs_index(l: LuaState, idx: i32) -> i32 {
+	if idx < 0 {
+		lua_gettop(l) + idx + 1
+	} else {
+		idx
+	}
+}
+
+impl<K: FromLua + Eq + Hash, V: FromLua> FromLua for HashMap<K, V> {
+	fn from_lua(l: LuaState, idx: i32) -> Result<Self, ConvError> {
+		match lua_type(l, idx) {
+			Type::Table => {
+				let idx = abs_index(l, idx);
+				// Guards against `K`/`V::from_lua` erroring mid-loop, which would
+				// otherwise leak the current key (and value, if the key converted fine)
+				// left on the stack by `lua_next`.
+				let _guard = Stack::new(l);
+				let mut out = HashMap::new();
+				// lua_next expects the key slot to hold the previous key, starting at nil.
+				lua_pushnil(l);
+				while lua_next(l, idx) != 0 {
+					let key = K::from_lua(l, -2)?;
+					let value = V::from_lua(l, -1)?;
+					out.insert(key, value);
+					lua_pop(l, 1); // Pop the value, keep the key for the next iteration.
+				}
+				Ok(out)
+			}
+			found => Err(ConvError {
+				expected: Type::Table,
+				found,
+				idx,
+			}),
+		}
+	}
+}
+
+impl<K: IntoLua, V: IntoLua> IntoLua for HashMap<K, V> {
+	fn into_lua(self, l: LuaState) {
+		lua_createtable(l, 0, self.len() as i32);
+		for (k, v) in self.into_iter() {
+			k.into_lua(l);
+			v.into_lua(l);
+			lua_settable(l, -3);
+		}
+	}
+}
+
+macro_rules! impl_tuple {
+	($($name:ident : $idx:literal),+) => {
+		impl<$($name: FromLua),+> FromLua for ($($name,)+) {
+			fn from_lua(l: LuaState, idx: i32) -> Result<Self, ConvError> {
+				let idx = abs_index(l, idx);
+				Ok(($($name::from_lua(l, idx + $idx)?,)+))
+			}
+		}
+
+		impl<$($name: IntoLua),+> IntoLua for ($($name,)+) {
+			#[allow(non_snake_case)]
+			fn into_lua(self, l: LuaState) {
+				let ($($name,)+) = self;
+				$($name.into_lua(l);)+
+			}
+		}
+	};
+}
+
+impl_tuple!(A: 0);
+impl_tuple!(A: 0, B: 1);
+impl_tuple!(A: 0, B: 1, C: 2);
+impl_tuple!(A: 0, B: 1, C: 2, D: 3);
+
+/// Snapshots `lua_gettop` on creation and restores it on drop.
+///
+/// Wrap a sequence of pushes/conversions in a [`Stack`] guard so a conversion failing
+/// partway through doesn't leave extra values sitting on the stack.
+pub struct Stack {
+	l: LuaState,
+	top: i32,
+}
+
+impl Stack {
+	pub fn new(l: LuaState) -> Self {
+		Self {
+			l,
+			top: lua_gettop(l),
+		}
+	}
+}
+
+impl Drop for Stack {
+	fn drop(&mut self) {
+		lua_settop(self.l, self.top);
+	}
+}
+
+/// Pushes `args` onto the stack via [`IntoLua`]. This is the counterpart to [`pop_return`]
+/// and exists mostly so call sites read as "push args, call, pop return" instead of reaching
+/// for `into_lua` directly.
+pub fn push_args(l: LuaState, args: impl IntoLua) {
+	args.into_lua(l);
+}
+
+/// Pops and converts a single return value from the top of the stack.
+pub fn pop_return<T: FromLua>(l: LuaState) -> Result<T, ConvError> {
+	let value = T::from_lua(l, -1);
+	lua_pop(l, 1);
+	value
+}
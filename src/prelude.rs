@@ -1,5 +1,10 @@
 pub use crate::lua::*;
 pub use crate::types::{LuaCFunction, LuaInteger, LuaNumber, LuaState, LuaString};
 
-pub use crate::util::dump_stack;
-pub use crate::{cstr, printgm, reg, rstr, try_cstr, try_rstr};
+pub use crate::closure::push_closure;
+pub use crate::conv::{pop_return, push_args, ConvError, FromLua, IntoLua, Stack};
+pub use crate::util::{
+	call_stack, dump_stack, inspect_stack, lua_string_bytes, traceback, CallInfo, LuaReference,
+	StackSlot,
+};
+pub use crate::{cstr, printgm, reg, reg_closure, rstr, rstr_lossy, try_cstr, try_rstr};
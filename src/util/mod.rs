@@ -83,6 +83,39 @@ macro_rules! try_rstr {
 	}};
 }
 
+/// Reads the string on the Lua stack at `idx` as a raw byte slice, via `lua_tolstring`'s
+/// size out-param, rather than `CStr::from_ptr`.
+///
+/// Lua strings are byte strings and may legitimately contain embedded NULs or non-UTF-8
+/// bytes; `rstr!`/`try_rstr!` go through `CStr::from_ptr`, which truncates at the first NUL
+/// and can't represent that data at all. Prefer this (or [`rstr_lossy!`](crate::rstr_lossy))
+/// whenever the string's origin isn't a known-UTF-8 literal.
+pub fn lua_string_bytes<'a>(l: LuaState, idx: i32) -> &'a [u8] {
+	use crate::lua::lua_tolstring;
+
+	let mut len: usize = 0;
+	let ptr = lua_tolstring(l, idx, &mut len);
+	unsafe { std::slice::from_raw_parts(ptr as *const u8, len) }
+}
+
+/// Like `rstr!`, but never panics on non-UTF-8 or truncates at embedded NULs: reads the
+/// full byte length via [`lua_string_bytes`] and converts with `String::from_utf8_lossy`.
+/// # Examples
+/// ```rust, no_run
+/// use rglua::prelude::*;
+/// fn gmod13_open(l: LuaState) -> i32 {
+///     let s = rstr_lossy!(l, 1);
+///     printgm!(l, "{}", s);
+///     0
+/// }
+/// ```
+#[macro_export]
+macro_rules! rstr_lossy {
+	($l:expr, $idx:expr) => {
+		String::from_utf8_lossy(rglua::util::lua_string_bytes($l, $idx))
+	};
+}
+
 #[allow(unused_macros)]
 #[macro_export]
 /// Like println!, however it prints to the gmod server's console.
@@ -98,6 +131,23 @@ macro_rules! try_rstr {
 /// }
 /// ```
 macro_rules! printgm {
+	// Same as the generic arm below, but appends a `util::traceback` of the call stack
+	// starting at `$lvl` levels up. Useful on error paths where the plain message alone
+	// won't say where the error originated from. The leading `@traceback` token marks this
+	// arm: `traceback = $lvl` alone is itself a valid (assignment) expression, whereas `@`
+	// cannot begin an expression, so no call meant for the generic arm can match this one.
+	($state:expr, @traceback = $lvl:expr, $($x:expr),*) => {
+		{
+			let mut printargs = format!( $($x,)* );
+			printargs.push('\n');
+			printargs.push_str(&rglua::util::traceback($state, "", $lvl));
+			if let Ok(fmt) = std::ffi::CString::new(printargs) {
+				rglua::lua::lua_getglobal( $state, rglua::cstr!("print") );
+				rglua::lua::lua_pushstring( $state, fmt.as_ptr() );
+				rglua::lua::lua_call( $state, 1, 0 );
+			}
+		}
+	};
 	($state:expr, $($x:expr),*) => {
 		{
 			let printargs = format!( $($x,)* );
@@ -132,7 +182,53 @@ macro_rules! reg {
 	};
 }
 
-use crate::types::LuaState;
+use crate::types::{LuaNumber, LuaState};
+/// A single slot on the Lua stack, read out into an owned, typed representation by
+/// [`inspect_stack`]. This is the structured counterpart to [`dump_stack`]'s formatted
+/// `String`, meant for programmatic inspection (and for tests to assert against, instead of
+/// scraping the debug string).
+#[derive(Debug, Clone, PartialEq)]
+pub enum StackSlot {
+	Number(LuaNumber),
+	Str(Vec<u8>),
+	Bool(bool),
+	Nil,
+	None,
+	Table(*const std::ffi::c_void),
+	Function(*const std::ffi::c_void),
+	UserData(*const std::ffi::c_void),
+	/// Any other type the crate's [`crate::lua::Type`] enum can report (thread,
+	/// light userdata, ...), identified by pointer since they have no scalar value.
+	Other(crate::lua::Type, *const std::ffi::c_void),
+}
+
+/// Reads every slot of the Lua stack into an owned [`StackSlot`] representation, without
+/// mutating the stack. [`dump_stack`] is a `Display`-style formatter layered on top of this,
+/// so the two can never drift apart.
+pub fn inspect_stack(l: LuaState) -> Vec<StackSlot> {
+	use crate::lua::{Type, *};
+
+	let top = lua_gettop(l);
+	let mut slots = Vec::with_capacity(top as usize);
+
+	for i in 1..=top {
+		let slot = match lua_type(l, i) {
+			Type::Number => StackSlot::Number(lua_tonumber(l, i)),
+			Type::String => StackSlot::Str(lua_string_bytes(l, i).to_vec()),
+			Type::Bool => StackSlot::Bool(lua_toboolean(l, i) == 1),
+			Type::Nil => StackSlot::Nil,
+			Type::None => StackSlot::None,
+			Type::Table => StackSlot::Table(lua_topointer(l, i)),
+			Type::Function => StackSlot::Function(lua_topointer(l, i)),
+			Type::UserData => StackSlot::UserData(lua_topointer(l, i)),
+			other => StackSlot::Other(other, lua_topointer(l, i)),
+		};
+		slots.push(slot);
+	}
+
+	slots
+}
+
 /// Returns the current state of the lua stack without affecting it.
 /// Comes out in this format:
 /// ```text
@@ -145,29 +241,158 @@ use crate::types::LuaState;
 pub fn dump_stack(l: LuaState) -> Result<String, std::fmt::Error> {
 	use std::fmt::Write;
 
-	use crate::lua::{Type, *};
 	let mut buf = String::new();
 
-	let top = lua_gettop(l);
-	for i in 1..=top {
-		write!(&mut buf, "[{}] '{}' = ", i, rstr!(luaL_typename(l, i)));
-		match lua_type(l, i) {
-			Type::Number => write!(&mut buf, "{}", lua_tonumber(l, i)),
-			Type::String => write!(&mut buf, "{}", rstr!(lua_tostring(l, i))),
-			Type::Bool => write!(
-				&mut buf,
-				"{}",
-				if lua_toboolean(l, i) == 1 {
-					"true"
-				} else {
-					"false"
-				}
-			),
-			Type::Nil => write!(&mut buf, "nil"),
-			Type::None => write!(&mut buf, "none"),
-			_ => write!(&mut buf, "{:p}", lua_topointer(l, i)),
+	for (i, slot) in inspect_stack(l).into_iter().enumerate() {
+		write!(&mut buf, "[{}] '", i + 1)?;
+		match slot {
+			StackSlot::Number(n) => write!(&mut buf, "number' = {}", n),
+			StackSlot::Str(bytes) => write!(&mut buf, "string' = {}", String::from_utf8_lossy(&bytes)),
+			StackSlot::Bool(b) => write!(&mut buf, "boolean' = {}", b),
+			StackSlot::Nil => write!(&mut buf, "nil' = nil"),
+			StackSlot::None => write!(&mut buf, "none' = none"),
+			StackSlot::Table(ptr) => write!(&mut buf, "table' = {:p}", ptr),
+			StackSlot::Function(ptr) => write!(&mut buf, "function' = {:p}", ptr),
+			StackSlot::UserData(ptr) => write!(&mut buf, "userdata' = {:p}", ptr),
+			StackSlot::Other(ty, ptr) => write!(&mut buf, "{:?}' = {:p}", ty, ptr),
 		}?
 	}
 
 	Ok(buf)
 }
+
+/// A safe handle to a value stashed in the Lua registry, the standard way to hold onto a
+/// Lua value (function, table, userdata, ...) across separate C calls without juggling
+/// stack slots by hand.
+///
+/// Construct one from a value sitting on top of the stack with [`LuaReference::new`], which
+/// pops it via `luaL_ref`. [`LuaReference::push`] puts a copy back on top of the stack
+/// whenever it's needed again, and [`Drop`] calls `luaL_unref` so the registry slot is
+/// freed automatically.
+///
+/// # Safety invariant
+/// The owning [`LuaState`] must outlive every [`LuaReference`] created from it: dropping a
+/// reference after its Lua state has been closed calls `luaL_unref` on a dangling state. If
+/// a reference needs to outlive the scope that would normally drop it, use [`LuaReference::into_raw`]
+/// and manage the registry key's lifetime yourself.
+pub struct LuaReference {
+	l: LuaState,
+	key: i32,
+}
+
+impl LuaReference {
+	/// Pops the value on top of the stack and stores it in the registry.
+	pub fn new(l: LuaState) -> Self {
+		use crate::lua::*;
+
+		let key = luaL_ref(l, LUA_REGISTRYINDEX);
+		Self { l, key }
+	}
+
+	/// Pushes the referenced value back onto the top of the stack.
+	pub fn push(&self, l: LuaState) {
+		use crate::lua::lua_rawgeti;
+
+		lua_rawgeti(l, crate::lua::LUA_REGISTRYINDEX, self.key);
+	}
+
+	/// Consumes the reference without unregistering it, returning the raw registry key.
+	/// Use this to hand lifetime management off to the caller; pair it with [`LuaReference::from_raw`]
+	/// to get a [`LuaReference`] back.
+	pub fn into_raw(self) -> i32 {
+		let key = self.key;
+		std::mem::forget(self);
+		key
+	}
+
+	/// Reconstructs a [`LuaReference`] from a raw registry key previously produced by
+	/// [`LuaReference::into_raw`].
+	pub fn from_raw(l: LuaState, key: i32) -> Self {
+		Self { l, key }
+	}
+}
+
+impl Drop for LuaReference {
+	fn drop(&mut self) {
+		use crate::lua::{luaL_unref, LUA_REGISTRYINDEX};
+
+		luaL_unref(self.l, LUA_REGISTRYINDEX, self.key);
+	}
+}
+
+/// One frame of a Lua call stack, as gathered by [`call_stack`].
+#[derive(Debug, Clone)]
+pub struct CallInfo {
+	/// The source of the function, e.g. `@path/to/file.lua` or a `=[C]` for native code.
+	pub source: String,
+	/// The line currently executing in this frame, or `-1` if unavailable (e.g. C functions).
+	/// Signed rather than `usize` specifically so that `-1` sentinel is representable
+	/// without a separate `Option`.
+	pub current_line: i32,
+	/// The function's name, if Lua could infer one from how it was called.
+	pub name: Option<String>,
+	/// What kind of function this is: `"Lua"`, `"C"`, `"main"` or `"tail"`.
+	pub what: String,
+}
+
+/// Walks the whole Lua call stack outward from level `0` (the currently running function,
+/// i.e. the C function calling `call_stack`), returning one [`CallInfo`] per frame.
+pub fn call_stack(l: LuaState) -> Vec<CallInfo> {
+	use crate::lua::{lua_getinfo, lua_getstack};
+	use crate::types::LuaDebug;
+
+	let mut frames = Vec::new();
+	let mut level = 0;
+
+	loop {
+		// SAFETY: `lua_Debug` is a plain-old-data C struct; `lua_getstack` fills it in
+		// before we read any field, and leaves it untouched on failure (0 return).
+		let mut debug: LuaDebug = unsafe { std::mem::zeroed() };
+
+		if unsafe { lua_getstack(l, level, &mut debug as *mut LuaDebug) } == 0 {
+			break;
+		}
+
+		// "S" source info, "l" current line, "n" name info.
+		unsafe { lua_getinfo(l, cstr!("Sln"), &mut debug as *mut LuaDebug) };
+
+		frames.push(CallInfo {
+			source: rstr!(debug.source).to_string(),
+			current_line: debug.currentline,
+			name: if debug.name.is_null() {
+				None
+			} else {
+				Some(rstr!(debug.name).to_string())
+			},
+			what: rstr!(debug.what).to_string(),
+		});
+
+		level += 1;
+	}
+
+	frames
+}
+
+/// Builds a human-readable traceback of the Lua call stack, starting `level` frames up,
+/// each line formatted as `source:line: in function 'name'` (mirroring the format Lua's own
+/// `debug.traceback` uses). `msg` is prepended as the first line when non-empty.
+pub fn traceback(l: LuaState, msg: &str, level: i32) -> String {
+	use std::fmt::Write;
+
+	let mut buf = String::new();
+	if !msg.is_empty() {
+		let _ = writeln!(&mut buf, "{}", msg);
+	}
+	let _ = write!(&mut buf, "stack traceback:");
+
+	for frame in call_stack(l).into_iter().skip(level.max(0) as usize) {
+		let name = frame.name.as_deref().unwrap_or("?");
+		let _ = write!(
+			&mut buf,
+			"\n\t{}:{}: in function '{}'",
+			frame.source, frame.current_line, name
+		);
+	}
+
+	buf
+}